@@ -6,58 +6,231 @@ use std::collections::HashMap;
 use std::convert::From;
 use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
 use actix_web::cookie::time::{Duration};
-use anyhow::{ Error};
+use anyhow::{anyhow, Error};
 use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
-use sqlx::{query, query_as, query_scalar, Database, Decode, Encode, Sqlite, SqlitePool, Type};
+use sqlx::{query, query_as, query_scalar, Database, Decode, Encode, FromRow, Pool, Sqlite, SqlitePool, Type};
+#[cfg(feature = "postgres")]
+use sqlx::Postgres;
 use sqlx::encode::IsNull;
 use sqlx::error::BoxDynError;
-use sqlx::sqlite::SqliteArgumentValue::Text;
+use sqlx::sqlite::{SqliteAutoVacuum, SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+use sqlx::types::Json;
 use tracing::{info_span, instrument};
 use tracing_futures::Instrument;
 use rand::random;
-use serde::{Deserialize, Serialize};
+use rand::rngs::OsRng;
+use rand::RngCore;
 use serde_json::Value;
+use std::time::Duration as StdDuration;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Duration as TokioDuration};
 
 pub type SessionState = HashMap<String, String>;
-pub struct SqliteSessionStore (pub SqlitePool);
 
-//Effectively uuid but fun!
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
-struct Uuid {
-	#[serde(with = "chrono::serde::ts_milliseconds", rename="t")]
-	timestamp: DateTime<Utc>,
-	#[serde(rename="r")]
-	random: u64
+/// Controls whether [`SqlxSessionStore::save`]/[`SqlxSessionStore::update`]
+/// persist a row for a session with no data in it (e.g. an anonymous visitor
+/// who never calls `session.insert`).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum PersistencePolicy {
+	/// Always write a row, even for an empty session. Matches the store's
+	/// historical behavior.
+	#[default]
+	Always,
+	/// Never create a row for an empty session, and delete the row backing
+	/// a session that becomes empty. Keeps anonymous/bot traffic from
+	/// filling up the `sessions` table.
+	ExistingOnly,
+}
+
+/// The SQL that differs between the `sqlx` databases this crate supports,
+/// for the shared `id`/`created`/`expires`/`data` session schema.
+pub trait SessionBackend: Database {
+	/// A boolean SQL expression (no leading `where`/`and`, no parameters)
+	/// that is true exactly when a row's `expires` column is in the past.
+	fn is_expired_sql() -> &'static str;
+}
+
+impl SessionBackend for Sqlite {
+	fn is_expired_sql() -> &'static str {
+		"julianday(expires) < julianday('now')"
+	}
+}
+
+#[cfg(feature = "postgres")]
+impl SessionBackend for Postgres {
+	fn is_expired_sql() -> &'static str {
+		"expires < now()"
+	}
+}
+
+/// A `SessionStore` backed by the `sessions` table (`id`, `created`,
+/// `expires`, `data`) on any [`SessionBackend`]. Use the [`SqliteSessionStore`]
+/// or [`PgSessionStore`] aliases rather than naming this directly.
+pub struct SqlxSessionStore<DB: Database> {
+	pool: Pool<DB>,
+	persistence_policy: PersistencePolicy,
+}
+
+/// A session store backed by SQLite.
+pub type SqliteSessionStore = SqlxSessionStore<Sqlite>;
+/// A session store backed by Postgres. Requires the `postgres` feature.
+#[cfg(feature = "postgres")]
+pub type PgSessionStore = SqlxSessionStore<Postgres>;
+
+impl<DB: SessionBackend> SqlxSessionStore<DB> {
+	pub fn new(pool: Pool<DB>) -> Self {
+		SqlxSessionStore { pool, persistence_policy: PersistencePolicy::Always }
+	}
+
+	pub fn with_persistence_policy(mut self, persistence_policy: PersistencePolicy) -> Self {
+		self.persistence_policy = persistence_policy;
+		self
+	}
+}
+
+/// Builds a [`SqliteSessionStore`] backed by a properly tuned connection
+/// pool: WAL journaling, a busy timeout so concurrent writers back off
+/// instead of erroring, incremental auto-vacuum so [`SqliteSessionStore::spawn_cleanup_task`]
+/// can actually reclaim space, and foreign keys enabled.
+pub struct SqliteSessionStoreBuilder {
+	filename: String,
+	create_if_missing: bool,
+	busy_timeout: StdDuration,
+	max_connections: u32,
+	min_connections: u32,
+	idle_timeout: Option<StdDuration>,
+	continuously_clean: Option<TokioDuration>,
+	persistence_policy: PersistencePolicy,
+}
+
+impl SqliteSessionStoreBuilder {
+	pub fn new(filename: impl Into<String>) -> Self {
+		SqliteSessionStoreBuilder {
+			filename: filename.into(),
+			create_if_missing: true,
+			busy_timeout: StdDuration::from_secs(5),
+			max_connections: 10,
+			min_connections: 0,
+			idle_timeout: None,
+			continuously_clean: None,
+			persistence_policy: PersistencePolicy::Always,
+		}
+	}
+
+	/// Convenience constructor for tests: each call gets its own isolated
+	/// database, but — unlike a bare `:memory:` filename — connections share
+	/// the same in-memory database, so the pool can still hand out more than
+	/// one connection at a time.
+	pub fn shared_in_memory_for_test() -> Self {
+		let id: u64 = random();
+		SqliteSessionStoreBuilder::new(format!("file:testdb-{id}?mode=memory&cache=shared"))
+	}
+
+	pub fn create_if_missing(mut self, create_if_missing: bool) -> Self {
+		self.create_if_missing = create_if_missing;
+		self
+	}
+
+	pub fn busy_timeout(mut self, busy_timeout: StdDuration) -> Self {
+		self.busy_timeout = busy_timeout;
+		self
+	}
+
+	pub fn max_connections(mut self, max_connections: u32) -> Self {
+		self.max_connections = max_connections;
+		self
+	}
+
+	pub fn min_connections(mut self, min_connections: u32) -> Self {
+		self.min_connections = min_connections;
+		self
+	}
+
+	pub fn idle_timeout(mut self, idle_timeout: StdDuration) -> Self {
+		self.idle_timeout = Some(idle_timeout);
+		self
+	}
+
+	/// When set, spawns [`SqliteSessionStore::spawn_cleanup_task`] as soon as
+	/// the store is built. The handle is not returned; if the application
+	/// needs to abort the sweep on shutdown it should call
+	/// `spawn_cleanup_task` itself instead of using this flag.
+	pub fn continuously_clean(mut self, sweep_interval: TokioDuration) -> Self {
+		self.continuously_clean = Some(sweep_interval);
+		self
+	}
+
+	pub fn persistence_policy(mut self, persistence_policy: PersistencePolicy) -> Self {
+		self.persistence_policy = persistence_policy;
+		self
+	}
+
+	#[instrument(skip(self), err)]
+	pub async fn build(self) -> Result<SqliteSessionStore, sqlx::Error> {
+		let options = SqliteConnectOptions::new()
+			.filename(&self.filename)
+			.create_if_missing(self.create_if_missing)
+			.foreign_keys(true)
+			.busy_timeout(self.busy_timeout)
+			.auto_vacuum(SqliteAutoVacuum::Incremental)
+			.journal_mode(SqliteJournalMode::Wal);
+
+		let mut pool_options = SqlitePoolOptions::new()
+			.max_connections(self.max_connections)
+			.min_connections(self.min_connections);
+		if let Some(idle_timeout) = self.idle_timeout {
+			pool_options = pool_options.idle_timeout(idle_timeout);
+		}
+
+		let pool = pool_options.connect_with(options).await?;
+		let store = SqliteSessionStore::new(pool).with_persistence_policy(self.persistence_policy);
+		if let Some(sweep_interval) = self.continuously_clean {
+			store.spawn_cleanup_task(sweep_interval);
+		}
+		Ok(store)
+	}
 }
 
+//Effectively uuid but fun!
+//128 bits of OsRng entropy, hex-encoded. No timestamp is embedded: unlike the
+//previous millisecond-timestamp-plus-u64 scheme, the key itself leaks nothing
+//about when the session was created - that's tracked only in the `created`
+//column (see DbSessionRow) so it never reaches the cookie.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Uuid([u8; 16]);
+
 impl Uuid {
 	fn new() -> Uuid {
-		Uuid {
-			timestamp: Utc::now(),
-			random: random()
-		}
+		let mut bytes = [0u8; 16];
+		OsRng.fill_bytes(&mut bytes);
+		Uuid(bytes)
 	}
 }
 
 impl TryFrom<&str> for Uuid {
-	type Error = serde_json::Error;
+	type Error = Error;
 
 	fn try_from(value: &str) -> Result<Self, Self::Error> {
-		serde_json::from_str(value)
+		if value.len() != 32 || !value.bytes().all(|b| b.is_ascii_hexdigit()) {
+			return Err(anyhow!("session key must be 32 hex characters"));
+		}
+		let mut bytes = [0u8; 16];
+		for (i, byte) in bytes.iter_mut().enumerate() {
+			*byte = u8::from_str_radix(&value[i * 2..i * 2 + 2], 16)?;
+		}
+		Ok(Uuid(bytes))
 	}
 }
 
 impl From<Uuid> for String {
 	fn from(val: Uuid) -> Self {
-		serde_json::to_string(&val).expect("")
+		val.0.iter().map(|b| format!("{:02x}", b)).collect()
 	}
 }
 
-impl From<Uuid> for DateTime<Utc> {
-	fn from(value: Uuid) -> Self {
-		value.timestamp
-	}
-}
+//Used internally by the shim to tell apart keys minted by this store (see
+//[`TryFrom<&str> for Uuid`]) from ones minted by another `SessionStore`.
+pub(crate) type StoreSessionKey = Uuid;
 
 impl From<Uuid> for SessionKey {
 	fn from(value: Uuid) -> Self {
@@ -65,33 +238,36 @@ impl From<Uuid> for SessionKey {
 	}
 }
 
-impl Type<Sqlite> for Uuid {
-	fn type_info() -> <Sqlite as Database>::TypeInfo {
-		<&String as Type<Sqlite>>::type_info()
+//Stored as text on every backend we support, so Type/Encode/Decode just
+//delegate to String/&str rather than repeating a per-backend impl.
+impl<DB: Database> Type<DB> for Uuid where String: Type<DB> {
+	fn type_info() -> DB::TypeInfo {
+		<String as Type<DB>>::type_info()
 	}
 }
 
-impl<'q> Encode<'q, Sqlite> for Uuid {
-	fn encode_by_ref(&self, buf: &mut <Sqlite as Database>::ArgumentBuffer<'q>) -> Result<IsNull, BoxDynError> {
+impl<'q, DB: Database> Encode<'q, DB> for Uuid where String: Encode<'q, DB> {
+	fn encode_by_ref(&self, buf: &mut <DB as Database>::ArgumentBuffer<'q>) -> Result<IsNull, BoxDynError> {
 		let encoded: String = (*self).into();
-		buf.push(Text(encoded.into()));
-		Ok(IsNull::No)
+		encoded.encode_by_ref(buf)
 	}
 }
 
-impl<'r> Decode<'r, Sqlite> for Uuid {
-	fn decode(value: <Sqlite as Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
-		let value = <&str as Decode<Sqlite>>::decode(value)?;
-		Uuid::try_from(value).map_err(|e| BoxDynError::from(e))
+impl<'r, DB: Database> Decode<'r, DB> for Uuid where &'r str: Decode<'r, DB> {
+	fn decode(value: <DB as Database>::ValueRef<'r>) -> Result<Self, BoxDynError> {
+		let value = <&str as Decode<DB>>::decode(value)?;
+		Uuid::try_from(value).map_err(BoxDynError::from)
 	}
 }
 
+#[derive(FromRow)]
 struct DbSessionRow {
 	#[allow(dead_code)]
 	pub id: Uuid,
 	pub expires: NaiveDateTime,
 	#[allow(dead_code)]
 	pub created: NaiveDateTime,
+	#[sqlx(json)]
 	pub data: Value
 }
 
@@ -101,27 +277,105 @@ fn convert_duration(duration: &Duration) -> TimeDelta {
 }
 
 impl SqliteSessionStore {
+	/// Opens a database at `path`, creating it if missing, with no further
+	/// tuning applied. Prefer [`SqliteSessionStoreBuilder`] for production
+	/// use, which also sets WAL mode, a busy timeout and incremental
+	/// auto-vacuum.
+	pub async fn open_with_path(path: &str) -> Result<Self, sqlx::Error> {
+		let pool = SqlitePool::connect_with(SqliteConnectOptions::new().filename(path).create_if_missing(true)).await?;
+		Ok(SqliteSessionStore::new(pool))
+	}
+
+	/// Spawns a background task that periodically deletes expired sessions and
+	/// runs `PRAGMA incremental_vacuum` to reclaim the freed pages.
+	///
+	/// The pool should be opened with `SqliteAutoVacuum::Incremental` for the
+	/// vacuum step to actually free space. Abort the returned handle to stop
+	/// the sweep, e.g. on application shutdown.
+	///
+	/// Per-sweep logging (its span and the deleted-row count) comes from
+	/// `clean_database`, which runs inside the spawned loop; this function itself just sets
+	/// the loop up and returns immediately, so it isn't instrumented.
+	pub fn spawn_cleanup_task(&self, sweep_interval: TokioDuration) -> JoinHandle<()> {
+		let pool = self.pool.clone();
+		tokio::spawn(async move {
+			let store = SqliteSessionStore::new(pool);
+			let mut ticker = interval(sweep_interval);
+			loop {
+				ticker.tick().await;
+				if let Err(e) = store.clean_database().await {
+					tracing::warn!(error = %e, "failed to sweep expired sessions");
+					continue;
+				}
+				if let Err(e) = query("PRAGMA incremental_vacuum").execute(&store.pool).await {
+					tracing::warn!(error = %e, "failed to run incremental vacuum");
+				}
+			}
+		})
+	}
+}
+
+impl<DB> SqlxSessionStore<DB>
+where
+	DB: SessionBackend,
+	for<'r> i64: Decode<'r, DB>,
+	i64: Type<DB>,
+{
 	#[instrument(skip(self), err)]
-	async fn clean_database(&self ) -> Result<u32, sqlx::Error> {
-		let mut t = self.0.begin().instrument(info_span!("Connecting to DB")).await?;
-		query!("delete from sessions where strftime('%s', expires) < unixepoch()").execute(&mut *t).instrument(info_span!("Deleting data")).await?;
-		let result = query_scalar!(r#"select changes() as "foo!:u32" from sessions"#).fetch_one(&mut *t).instrument(info_span!("Querying changes")).await?;
+	async fn clean_database(&self) -> Result<u64, sqlx::Error> {
+		let mut t = self.pool.begin().instrument(info_span!("Connecting to DB")).await?;
+		let sql = format!("delete from sessions where {}", DB::is_expired_sql());
+		let result = query(&sql).execute(&mut *t).instrument(info_span!("Deleting data")).await?;
 		t.commit().await?;
-		Ok(result)
+		let deleted = result.rows_affected();
+		tracing::info!(deleted, "swept expired sessions");
+		Ok(deleted)
+	}
+
+	/// Deletes every session row, expired or not. Useful when a server's
+	/// signing secret rotates and all prior sessions must be invalidated.
+	#[instrument(skip(self), err)]
+	pub async fn clear_store(&self) -> Result<(), sqlx::Error> {
+		query("delete from sessions").execute(&self.pool).await?;
+		Ok(())
+	}
+
+	/// Returns the number of currently non-expired sessions.
+	#[instrument(skip(self), err)]
+	pub async fn count(&self) -> Result<i64, sqlx::Error> {
+		let sql = format!("select count(*) from sessions where not ({})", DB::is_expired_sql());
+		query_scalar::<DB, i64>(&sql).fetch_one(&self.pool).await
+	}
+
+	/// Deletes all expired sessions and returns how many rows were removed.
+	#[instrument(skip(self), err)]
+	pub async fn delete_expired(&self) -> Result<u64, sqlx::Error> {
+		self.clean_database().await
 	}
 }
 
-impl SessionStore for SqliteSessionStore {
+impl<DB> SessionStore for SqlxSessionStore<DB>
+where
+	DB: SessionBackend,
+	for<'q> Uuid: Encode<'q, DB> + Type<DB>,
+	for<'q> DateTime<Utc>: Encode<'q, DB> + Type<DB>,
+	for<'q> Json<Value>: Encode<'q, DB> + Type<DB>,
+	for<'r> DbSessionRow: FromRow<'r, DB::Row>,
+{
 	#[instrument(skip(self), err)]
 	async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
 		let key= Uuid::try_from(session_key.as_ref()).map_err(|e| LoadError::Other(Error::from(e)))?;
-		let mut t = self.0.begin().instrument(info_span!("Connecting to DB")).await.map_err(|e| LoadError::Other(Error::from(e)))?;
-		let row = query_as!(DbSessionRow, r#"select id as "id!: Uuid", expires, created,data from sessions where id=$1"#, key).fetch_optional(&mut *t)
+		let mut t = self.pool.begin().instrument(info_span!("Connecting to DB")).await.map_err(|e| LoadError::Other(Error::from(e)))?;
+		let row = query_as::<DB, DbSessionRow>("select id, expires, created, data from sessions where id = $1")
+			.bind(key)
+			.fetch_optional(&mut *t)
 			.instrument(info_span!("Querying data"))
 			.await.map_err(|e|  LoadError::Other(Error::from(e)))?;
 		if row.as_ref().is_some_and(|x| x.expires < Utc::now().naive_utc()) {
 			//In case we've queried an expired row, drop it
-			query!("delete from sessions where id=$1", key).execute(&mut *t)
+			query("delete from sessions where id = $1")
+				.bind(key)
+				.execute(&mut *t)
 				.instrument(info_span!("Dropping timed-out session"))
 				.await.map_err(|e|  LoadError::Other(Error::from(e)))?;
 			t.commit().await.map_err(|e| LoadError::Other(Error::from(e)))?;
@@ -132,24 +386,51 @@ impl SessionStore for SqliteSessionStore {
 
 	#[instrument(skip(self), err)]
 	async fn save(&self, session_state: SessionState, ttl: &Duration) -> Result<SessionKey, SaveError> {
-		let value = serde_json::to_value(session_state).map_err(|e| SaveError::Serialization(Error::from(e)))?;
 		let new_key = Uuid::new();
+		if self.persistence_policy == PersistencePolicy::ExistingOnly && session_state.is_empty() {
+			return Ok(new_key.into());
+		}
+		let value = serde_json::to_value(session_state).map_err(|e| SaveError::Serialization(Error::from(e)))?;
 		let now = Utc::now();
 		let expires = now + convert_duration(ttl);
-		let id = query_scalar!(r#"insert into sessions (id, created, expires, data) values ($1, $2, $3, $4) returning id as "id!: Uuid""#,
-			new_key, now, expires, value)
-			.fetch_one(&self.0)
+		query("insert into sessions (id, created, expires, data) values ($1, $2, $3, $4)")
+			.bind(new_key)
+			.bind(now)
+			.bind(expires)
+			.bind(Json(value))
+			.execute(&self.pool)
 			.await.map_err(|e|  SaveError::Other(Error::from(e)))?;
-		Ok(id.into())
+		Ok(new_key.into())
 	}
 
 	#[instrument(skip(self), err)]
 	async fn update(&self, session_key: SessionKey, session_state:SessionState, ttl: &Duration) -> Result<SessionKey, UpdateError> {
 		let key = Uuid::try_from(session_key.as_ref()).map_err(|e| UpdateError::Other(Error::from(e)))?;
+		if self.persistence_policy == PersistencePolicy::ExistingOnly && session_state.is_empty() {
+			query("delete from sessions where id = $1").bind(key).execute(&self.pool).await
+				.map_err(|e| UpdateError::Other(Error::from(e)))?;
+			return Ok(session_key);
+		}
 		let value = serde_json::to_value(session_state).map_err(|e| UpdateError::Serialization(Error::from(e)))?;
 		let expires =  Utc::now() + convert_duration(ttl);
-		query!("update sessions set data=$2, expires=$3 where id=$1", key, value, expires).execute(&self.0).await
+		let result = query("update sessions set data = $2, expires = $3 where id = $1")
+			.bind(key)
+			.bind(Json(value.clone()))
+			.bind(expires)
+			.execute(&self.pool).await
 			.map_err(|e| UpdateError::Other(Error::from(e)))?;
+		if result.rows_affected() == 0 {
+			//Under `PersistencePolicy::ExistingOnly`, `save` of an empty session
+			//never inserted a row for this key - insert it now instead of
+			//silently dropping the update.
+			query("insert into sessions (id, created, expires, data) values ($1, $2, $3, $4)")
+				.bind(key)
+				.bind(Utc::now())
+				.bind(expires)
+				.bind(Json(value))
+				.execute(&self.pool).await
+				.map_err(|e| UpdateError::Other(Error::from(e)))?;
+		}
 
 		Ok(session_key)
 	}
@@ -158,7 +439,10 @@ impl SessionStore for SqliteSessionStore {
 	async fn update_ttl(&self, session_key: &SessionKey, ttl: &Duration) -> Result<(), Error> {
 		let key = Uuid::try_from(session_key.as_ref()).map_err(|e| UpdateError::Other(Error::from(e)))?;
 		let expires =  Utc::now() + convert_duration(ttl);
-		query!("update sessions set expires=$2 where id=$1", key, expires).execute(&self.0).await
+		query("update sessions set expires = $2 where id = $1")
+			.bind(key)
+			.bind(expires)
+			.execute(&self.pool).await
 			.map_err(|e| UpdateError::Other(Error::from(e)))?;
 
 		Ok(())
@@ -167,38 +451,155 @@ impl SessionStore for SqliteSessionStore {
 	#[instrument(skip(self), err)]
 	async fn delete(&self, session_key: &SessionKey) -> Result<(), Error> {
 		let key = Uuid::try_from(session_key.as_ref()).map_err(|e| UpdateError::Other(Error::from(e)))?;
-		query!("delete from sessions where id=$1", key).execute(&self.0)
+		query("delete from sessions where id = $1")
+			.bind(key)
+			.execute(&self.pool)
 			.await.map_err(|e|  LoadError::Other(Error::from(e)))?;
 		Ok(())
 	}
 }
 
+//`test_one` below only ever instantiates `DB = Sqlite`, so nothing else
+//verifies that `PgSessionStore` actually satisfies the bounds `SessionStore`
+//and the admin methods require. This function is never called - it exists
+//purely so a bound regression on the Postgres side fails `cargo build`
+//instead of going unnoticed. Gated behind the `postgres` feature so a
+//pure-SQLite build doesn't pull in the Postgres driver just to compile this.
+#[cfg(feature = "postgres")]
+#[allow(dead_code)]
+fn _pg_session_store_compiles(pool: sqlx::PgPool) {
+	fn assert_session_store<S: SessionStore>(_: &S) {}
+	let store: PgSessionStore = SqlxSessionStore::new(pool).with_persistence_policy(PersistencePolicy::ExistingOnly);
+	assert_session_store(&store);
+	let _ = store.clear_store();
+	let _ = store.count();
+	let _ = store.delete_expired();
+}
+
 #[cfg(test)]
 mod test {
 	use std::collections::HashMap;
+	use std::time::Duration as StdDuration;
 	use actix_session::storage::SessionStore;
 	use actix_web::cookie::time::Duration;
-	use chrono::{DateTime, Utc};
-	use sqlx::{migrate, SqlitePool};
+	use sqlx::{migrate, query_scalar, SqlitePool};
 	use sqlx::sqlite::SqliteConnectOptions;
-	use crate::{SqliteSessionStore, Uuid};
+	use tokio::time::Duration as TokioDuration;
+	use crate::{PersistencePolicy, SqliteSessionStore, SqliteSessionStoreBuilder};
+
+	#[tokio::test]
+	async fn test_spawn_cleanup_task_sweeps_expired_sessions() {
+		let sess = SqliteSessionStoreBuilder::shared_in_memory_for_test().build().await.unwrap();
+		migrate!().run(&sess.pool).await.unwrap();
+
+		let data = HashMap::from([("a".to_string(), "b".to_string())]);
+		sess.save(data, &Duration::milliseconds(1)).await.unwrap();
+		tokio::time::sleep(StdDuration::from_millis(20)).await;
+
+		let handle = sess.spawn_cleanup_task(TokioDuration::from_millis(10));
+		tokio::time::sleep(StdDuration::from_millis(50)).await;
+		handle.abort();
+
+		let rows: i64 = query_scalar("select count(*) from sessions").fetch_one(&sess.pool).await.unwrap();
+		assert_eq!(rows, 0);
+	}
+
+	#[tokio::test]
+	async fn test_clear_store_and_delete_expired() {
+		let sess = SqliteSessionStoreBuilder::shared_in_memory_for_test().build().await.unwrap();
+		migrate!().run(&sess.pool).await.unwrap();
+
+		let data = HashMap::from([("a".to_string(), "b".to_string())]);
+		sess.save(data.clone(), &Duration::milliseconds(1)).await.unwrap();
+		sess.save(data, &Duration::hours(1)).await.unwrap();
+		tokio::time::sleep(StdDuration::from_millis(20)).await;
+
+		assert_eq!(sess.delete_expired().await.unwrap(), 1);
+		assert_eq!(sess.count().await.unwrap(), 1);
+
+		sess.clear_store().await.unwrap();
+		assert_eq!(sess.count().await.unwrap(), 0);
+	}
+
+	#[tokio::test]
+	async fn test_builder_tunes_pool_and_produces_usable_store() {
+		let sess = SqliteSessionStoreBuilder::shared_in_memory_for_test()
+			.max_connections(2)
+			.min_connections(1)
+			.busy_timeout(StdDuration::from_secs(1))
+			.build().await.unwrap();
+		migrate!().run(&sess.pool).await.unwrap();
+
+		let data = HashMap::from([("a".to_string(), "b".to_string())]);
+		let key = sess.save(data.clone(), &Duration::hours(1)).await.unwrap();
+		assert_eq!(sess.load(&key).await.unwrap(), Some(data));
+	}
+
+	#[tokio::test]
+	async fn test_existing_only_update_after_empty_save() {
+		let sess = SqliteSessionStoreBuilder::shared_in_memory_for_test()
+			.persistence_policy(PersistencePolicy::ExistingOnly)
+			.build().await.unwrap();
+		migrate!().run(&sess.pool).await.unwrap();
+
+		let empty = HashMap::new();
+		let key = sess.save(empty, &Duration::hours(1)).await.unwrap();
+		assert_eq!(sess.count().await.unwrap(), 0);
+
+		let data = HashMap::from([("a".to_string(), "b".to_string())]);
+		let key = sess.update(key, data.clone(), &Duration::hours(1)).await.unwrap();
+
+		assert_eq!(sess.load(&key).await.unwrap(), Some(data));
+	}
 
 	#[tokio::test]
 	async fn test_one() {
 		let pool = SqlitePool::connect_with(SqliteConnectOptions::new().filename("debug.db")
 			.create_if_missing(true)).await.unwrap();
 		migrate!().run(&pool).await.unwrap();
-		let sess = SqliteSessionStore(pool);
-		
+		let sess = SqliteSessionStore::new(pool);
+
 		let data1 = HashMap::from([("1".to_string(), "loremp".to_string()), ("2".to_string(), "Ipsum".to_string())]);
 
 		let key1 = sess.save(data1.clone(), &Duration::hours(1)).await.unwrap();
 		println!("{:?}", key1);
 		let key2 = sess.save(data1, &Duration::hours(1)).await.unwrap();
 
-		println!("{}", Into::<DateTime<Utc>>::into(Uuid::try_from(key2.as_ref()).expect("")));
 		println!("{:?}", key2);
 
 		assert_ne!(key1, key2);
 	}
-}
\ No newline at end of file
+}
+
+//Exercises `PgSessionStore` against a real server, since `_pg_session_store_compiles`
+//only proves the bounds are satisfiable, not that the SQL in `SessionBackend for
+//Postgres` is correct. Requires a scratch Postgres reachable at
+//`POSTGRES_TEST_DATABASE_URL`, e.g. `docker run -e POSTGRES_PASSWORD=postgres -p 5432:5432 postgres`.
+#[cfg(all(test, feature = "postgres", feature = "postgres-tests"))]
+mod postgres_test {
+	use std::collections::HashMap;
+	use actix_session::storage::SessionStore;
+	use actix_web::cookie::time::Duration;
+	use sqlx::{migrate, PgPool};
+	use crate::{PersistencePolicy, PgSessionStore, SqlxSessionStore};
+
+	#[tokio::test]
+	async fn test_postgres_backend() {
+		let url = std::env::var("POSTGRES_TEST_DATABASE_URL")
+			.expect("POSTGRES_TEST_DATABASE_URL must point at a scratch Postgres database");
+		let pool = PgPool::connect(&url).await.unwrap();
+		migrate!().run(&pool).await.unwrap();
+		let sess: PgSessionStore = SqlxSessionStore::new(pool).with_persistence_policy(PersistencePolicy::ExistingOnly);
+
+		let empty = HashMap::new();
+		let key = sess.save(empty, &Duration::hours(1)).await.unwrap();
+		assert_eq!(sess.count().await.unwrap(), 0);
+
+		let data = HashMap::from([("a".to_string(), "b".to_string())]);
+		let key = sess.update(key, data.clone(), &Duration::hours(1)).await.unwrap();
+		assert_eq!(sess.load(&key).await.unwrap(), Some(data));
+
+		sess.clear_store().await.unwrap();
+		assert_eq!(sess.count().await.unwrap(), 0);
+	}
+}